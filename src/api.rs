@@ -1,12 +1,17 @@
 use crate::config::Config;
 use anyhow::{bail, Context, Result};
 use reqwest::blocking::{multipart::Form, Client, RequestBuilder};
-use reqwest::header;
+use reqwest::{header, Proxy};
 use serde::Deserialize;
 use std::env;
 use std::fmt::Debug;
+use std::thread;
+use std::time::Duration;
 use url::Url;
 
+const RETRY_MAX_ATTEMPTS: u32 = 3;
+const RETRY_BASE_DELAY: Duration = Duration::from_millis(500);
+
 #[derive(Debug, Deserialize)]
 pub struct UploadAsciicastResponse {
     pub url: String,
@@ -31,10 +36,56 @@ pub fn get_auth_url(config: &Config) -> Result<Url> {
     Ok(url)
 }
 
+// Retries on connection errors, timeouts and 5xx responses, doubling the delay
+// after each attempt. Other errors are returned right away.
+pub fn with_retry<T>(
+    mut f: impl FnMut() -> Result<T>,
+    max_attempts: u32,
+    base_delay: Duration,
+    mut sleep: impl FnMut(Duration),
+) -> Result<T> {
+    let mut attempt = 1;
+
+    loop {
+        match f() {
+            Ok(value) => return Ok(value),
+
+            Err(e) if attempt < max_attempts && is_transient(&e) => {
+                sleep(base_delay * 2u32.pow(attempt - 1));
+                attempt += 1;
+            }
+
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+fn is_transient(error: &anyhow::Error) -> bool {
+    error.downcast_ref::<reqwest::Error>().is_some_and(|e| {
+        e.is_connect() || e.is_timeout() || e.status().is_some_and(|s| s.is_server_error())
+    })
+}
+
 pub fn upload_asciicast(path: &str, config: &Config) -> Result<UploadAsciicastResponse> {
     let server_url = &config.get_server_url()?;
     let install_id = config.get_install_id()?;
-    let response = upload_request(server_url, path, install_id)?.send()?;
+    let client = build_client(config.get_server_proxy())?;
+
+    with_retry(
+        || upload(&client, server_url, path, install_id.clone()),
+        RETRY_MAX_ATTEMPTS,
+        RETRY_BASE_DELAY,
+        thread::sleep,
+    )
+}
+
+fn upload(
+    client: &Client,
+    server_url: &Url,
+    path: &str,
+    install_id: String,
+) -> Result<UploadAsciicastResponse> {
+    let response = upload_request(client, server_url, path, install_id)?.send()?;
 
     if response.status().as_u16() == 413 {
         bail!("The size of the recording exceeds the server's configured limit");
@@ -45,8 +96,12 @@ pub fn upload_asciicast(path: &str, config: &Config) -> Result<UploadAsciicastRe
     Ok(response.json::<UploadAsciicastResponse>()?)
 }
 
-fn upload_request(server_url: &Url, path: &str, install_id: String) -> Result<RequestBuilder> {
-    let client = Client::new();
+fn upload_request(
+    client: &Client,
+    server_url: &Url,
+    path: &str,
+    install_id: String,
+) -> Result<RequestBuilder> {
     let mut url = server_url.clone();
     url.set_path("api/asciicasts");
     let form = Form::new().file("asciicast", path)?;
@@ -63,8 +118,9 @@ pub fn create_user_stream(stream_id: String, config: &Config) -> Result<GetUserS
     let server_url = config.get_server_url()?;
     let server_hostname = server_url.host().unwrap();
     let install_id = config.get_install_id()?;
+    let client = build_client(config.get_server_proxy())?;
 
-    let response = user_stream_request(&server_url, stream_id, install_id)
+    let response = user_stream_request(&client, &server_url, stream_id, install_id)
         .send()
         .context("cannot obtain stream producer endpoint")?;
 
@@ -88,8 +144,12 @@ pub fn create_user_stream(stream_id: String, config: &Config) -> Result<GetUserS
         .map_err(|e| e.into())
 }
 
-fn user_stream_request(server_url: &Url, stream_id: String, install_id: String) -> RequestBuilder {
-    let client = Client::new();
+fn user_stream_request(
+    client: &Client,
+    server_url: &Url,
+    stream_id: String,
+    install_id: String,
+) -> RequestBuilder {
     let mut url = server_url.clone();
 
     let builder = if stream_id.is_empty() {
@@ -106,6 +166,18 @@ fn user_stream_request(server_url: &Url, stream_id: String, install_id: String)
         .header(header::ACCEPT, "application/json")
 }
 
+// Without an explicit proxy, reqwest uses HTTP_PROXY, HTTPS_PROXY and NO_PROXY
+// from the environment.
+fn build_client(proxy: Option<&str>) -> Result<Client> {
+    let mut builder = Client::builder();
+
+    if let Some(proxy) = proxy {
+        builder = builder.proxy(Proxy::all(proxy).context("invalid proxy URL")?);
+    }
+
+    Ok(builder.build()?)
+}
+
 fn get_username() -> String {
     env::var("USER").unwrap_or("".to_owned())
 }
@@ -120,3 +192,125 @@ fn build_user_agent() -> String {
 
     ua.to_owned()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::UploadAsciicastResponse;
+    use anyhow::Result;
+    use reqwest::blocking::Client;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::thread;
+    use std::time::Duration;
+    use url::Url;
+
+    const UPLOAD_RESPONSE: &str = r#"{"url": "https://asciinema.org/a/123", "message": null}"#;
+
+    fn serve(responses: Vec<(u16, &'static str)>) -> Url {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = Url::parse(&format!("http://{}", listener.local_addr().unwrap())).unwrap();
+
+        thread::spawn(move || {
+            for (status, body) in responses {
+                let (stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream);
+                let mut line = String::new();
+                let mut content_length = 0;
+
+                while reader.read_line(&mut line).unwrap() > 2 {
+                    if let Some((name, value)) = line.split_once(':') {
+                        if name.eq_ignore_ascii_case("content-length") {
+                            content_length = value.trim().parse().unwrap();
+                        }
+                    }
+
+                    line.clear();
+                }
+
+                // closing the socket with unread request data makes the
+                // client see a connection reset instead of the response
+                let mut request_body = vec![0; content_length];
+                reader.read_exact(&mut request_body).unwrap();
+
+                let response = format!(
+                    "HTTP/1.1 {status} X\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                    body.len()
+                );
+
+                reader.get_mut().write_all(response.as_bytes()).unwrap();
+            }
+        });
+
+        url
+    }
+
+    fn upload(client: &Client, server_url: &Url) -> Result<UploadAsciicastResponse> {
+        super::upload(client, server_url, "tests/casts/demo.cast", "id".to_owned())
+    }
+
+    #[test]
+    fn with_retry() {
+        let url = serve(vec![(500, "{}"), (503, "{}"), (200, UPLOAD_RESPONSE)]);
+        let mut sleeps = Vec::new();
+
+        let response = super::with_retry(
+            || upload(&Client::new(), &url),
+            3,
+            Duration::from_millis(100),
+            |d| sleeps.push(d),
+        )
+        .unwrap();
+
+        assert_eq!(response.url, "https://asciinema.org/a/123");
+        assert_eq!(
+            sleeps,
+            vec![Duration::from_millis(100), Duration::from_millis(200)]
+        );
+    }
+
+    #[test]
+    fn with_retry_gives_up() {
+        let url = serve(vec![(500, "{}"), (500, "{}"), (404, "{}")]);
+        let mut attempts = 0;
+
+        let result = super::with_retry(
+            || {
+                attempts += 1;
+
+                upload(&Client::new(), &url)
+            },
+            2,
+            Duration::ZERO,
+            |_| (),
+        );
+
+        assert!(result.is_err());
+        assert_eq!(attempts, 2);
+
+        let mut attempts = 0;
+
+        let result = super::with_retry(
+            || {
+                attempts += 1;
+
+                upload(&Client::new(), &url)
+            },
+            3,
+            Duration::ZERO,
+            |_| (),
+        );
+
+        assert!(result.is_err());
+        assert_eq!(attempts, 1);
+    }
+
+    #[test]
+    fn build_client_with_proxy() {
+        let proxy = serve(vec![(200, UPLOAD_RESPONSE)]);
+        let client = super::build_client(Some(proxy.as_str())).unwrap();
+        let server_url = Url::parse("http://asciinema.invalid").unwrap();
+        let response = upload(&client, &server_url).unwrap();
+
+        assert_eq!(response.url, "https://asciinema.org/a/123");
+    }
+}
@@ -1,15 +1,23 @@
 mod util;
 mod v1;
 mod v2;
+mod v3;
 use crate::tty;
-use anyhow::{anyhow, Result};
-use std::collections::HashMap;
+use anyhow::{anyhow, bail, Context, Result};
+use flate2::bufread::GzDecoder;
+use std::collections::BTreeMap;
+use std::env;
+use std::fmt;
 use std::fs;
-use std::io::{self, BufRead};
+use std::io::{self, BufRead, Write};
 use std::path::Path;
-pub use v2::Encoder;
+pub use v2::{Encoder, Writer};
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+const DEFAULT_ENV_VARS: [&str; 2] = ["SHELL", "TERM"];
 
 pub struct Asciicast<'a> {
+    pub version: u8,
     pub header: Header,
     pub events: Box<dyn Iterator<Item = Result<Event>> + 'a>,
 }
@@ -21,21 +29,44 @@ pub struct Header {
     pub idle_time_limit: Option<f64>,
     pub command: Option<String>,
     pub title: Option<String>,
-    pub env: Option<HashMap<String, String>>,
+    pub env: Option<BTreeMap<String, String>>,
     pub theme: Option<tty::Theme>,
+    pub extra: serde_json::Map<String, serde_json::Value>,
 }
 
+#[derive(Clone)]
 pub struct Event {
     pub time: u64,
     pub data: EventData,
 }
 
+#[derive(Debug)]
+pub enum Error {
+    Empty,
+    UnsupportedVersion(u8),
+    MissingSize,
+    Io(io::Error),
+    Json(serde_json::Error),
+}
+
+// Timing adjustments applied to the events of a recording on playback or
+// conversion. Unset fields leave the timing as recorded.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Timing {
+    pub idle_time_limit: Option<f64>,
+    pub speed: Option<f64>,
+}
+
+// Event data is always valid UTF-8, as asciicast stores it in JSON strings.
+// Invalid byte sequences coming from the PTY are replaced with U+FFFD by
+// crate::util::Utf8Decoder before events are created.
+#[derive(Clone)]
 pub enum EventData {
     Output(String),
     Input(String),
     Resize(u16, u16),
     Marker(String),
-    Other(char, String),
+    Other(String, String),
 }
 
 impl Default for Header {
@@ -49,40 +80,187 @@ impl Default for Header {
             title: None,
             env: None,
             theme: None,
+            extra: Default::default(),
         }
     }
 }
 
+impl Header {
+    // Env vars saved in the header when no allowlist is given
+    pub fn default_env() -> BTreeMap<String, String> {
+        Self::default_env_from(env::vars())
+    }
+
+    fn default_env_from(vars: impl Iterator<Item = (String, String)>) -> BTreeMap<String, String> {
+        filter_env(vars, &DEFAULT_ENV_VARS)
+    }
+}
+
+pub fn v2_header_schema() -> serde_json::Value {
+    v2::header_schema()
+}
+
+pub fn filter_env(
+    vars: impl Iterator<Item = (String, String)>,
+    allowlist: &[&str],
+) -> BTreeMap<String, String> {
+    vars.filter(|(k, _v)| allowlist.contains(&k.as_str()))
+        .collect()
+}
+
 pub fn open_from_path<S: AsRef<Path>>(path: S) -> Result<Asciicast<'static>> {
     fs::File::open(path)
         .map(io::BufReader::new)
-        .map_err(|e| anyhow!(e))
-        .and_then(open)
-        .map_err(|e| anyhow!("can't open asciicast file: {e}"))
+        .map_err(|e| anyhow!(Error::Io(e)))
+        .and_then(open_maybe_gzipped)
+        .context("can't open asciicast file")
+}
+
+// StdinLock is 'static, so reading from stdin yields an Asciicast which
+// doesn't borrow from the caller, same as when reading from a file.
+pub fn open_from_path_or_stdin<S: AsRef<Path>>(path: S) -> Result<Asciicast<'static>> {
+    if path.as_ref() == Path::new("-") {
+        open_maybe_gzipped(io::stdin().lock()).context("can't read asciicast from stdin")
+    } else {
+        open_from_path(path)
+    }
+}
+
+fn open_maybe_gzipped<'a, R: BufRead + 'a>(mut reader: R) -> Result<Asciicast<'a>> {
+    if reader
+        .fill_buf()
+        .map_err(Error::Io)?
+        .starts_with(&GZIP_MAGIC)
+    {
+        open(io::BufReader::new(GzDecoder::new(reader)))
+    } else {
+        open(reader)
+    }
 }
 
 pub fn open<'a, R: BufRead + 'a>(reader: R) -> Result<Asciicast<'a>> {
     let mut lines = reader.lines();
-    let first_line = lines.next().ok_or(anyhow!("empty file"))??;
+    let mut first_line = lines.next().ok_or(Error::Empty)?.map_err(Error::Io)?;
+
+    // some editors prepend a byte order mark when saving UTF-8 files
+    if let Some(line) = first_line.strip_prefix('\u{feff}') {
+        first_line = line.to_owned();
+    }
+
+    // pretty-printed headers (e.g. reformatted with jq) span multiple lines,
+    // up to the closing brace at the start of a line
+    if first_line.trim_end() == "{" {
+        for line in lines.by_ref() {
+            let line = line.map_err(Error::Io)?;
+            first_line.push('\n');
+            first_line.push_str(&line);
+
+            if line.starts_with('}') {
+                break;
+            }
+        }
+    }
 
     if let Ok(parser) = v2::open(&first_line) {
         Ok(parser.parse(lines))
+    } else if let Ok(parser) = v3::open(&first_line) {
+        Ok(parser.parse(lines))
+    } else if let Some(e) = header_error(&first_line) {
+        bail!(e)
     } else {
         let json = std::iter::once(Ok(first_line))
             .chain(lines)
-            .collect::<io::Result<String>>()?;
+            .collect::<io::Result<String>>()
+            .map_err(Error::Io)?;
 
         v1::load(json)
     }
 }
 
+// Explains why a single-line header couldn't be parsed by any of the
+// supported versions, when it's more specific than the v1 parse error.
+fn header_error(line: &str) -> Option<Error> {
+    let header = serde_json::from_str::<serde_json::Value>(line).ok()?;
+    let version = header["version"].as_u64()?;
+
+    match version {
+        2 if header["width"].is_null() || header["height"].is_null() => Some(Error::MissingSize),
+        1..=3 => None,
+        v => Some(Error::UnsupportedVersion(
+            u8::try_from(v).unwrap_or(u8::MAX),
+        )),
+    }
+}
+
 pub fn get_duration<S: AsRef<Path>>(path: S) -> Result<u64> {
     let Asciicast { events, .. } = open_from_path(path)?;
+
+    duration_of(events)
+}
+
+pub fn duration_of(events: impl Iterator<Item = Result<Event>>) -> Result<u64> {
     let time = events.last().map_or(Ok(0), |e| e.map(|e| e.time))?;
 
     Ok(time)
 }
 
+pub fn concat<'a, W: Write>(
+    recordings: impl IntoIterator<Item = Result<Asciicast<'a>>>,
+    writer: &mut Writer<W>,
+) -> Result<()> {
+    let mut time_offset = 0;
+    let mut size = None;
+
+    for recording in recordings {
+        let Asciicast { header, events, .. } = recording?;
+        let mut time = time_offset;
+
+        match size {
+            None => {
+                writer.write_header(&header)?;
+            }
+
+            Some(size) if size != (header.cols, header.rows) => {
+                writer.write_event(&Event::resize(time_offset, (header.cols, header.rows)))?;
+            }
+
+            _ => (),
+        }
+
+        size = Some((header.cols, header.rows));
+
+        for event in events {
+            let mut event = event?;
+            time = time_offset + event.time;
+            event.time = time;
+
+            if let EventData::Resize(cols, rows) = event.data {
+                size = Some((cols, rows));
+            }
+
+            writer.write_event(&event)?;
+        }
+
+        time_offset = time;
+    }
+
+    Ok(())
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Empty => f.write_str("empty file"),
+            Error::UnsupportedVersion(v) => write!(f, "unsupported asciicast version: {v}"),
+            Error::MissingSize => f.write_str("header missing width/height"),
+            Error::Io(e) => write!(f, "{e}"),
+            Error::Json(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
 impl Event {
     pub fn output(time: u64, text: String) -> Self {
         Event {
@@ -117,50 +295,110 @@ pub fn limit_idle_time(
     events: impl Iterator<Item = Result<Event>>,
     limit: f64,
 ) -> impl Iterator<Item = Result<Event>> {
-    let limit = (limit * 1_000_000.0) as u64;
-    let mut prev_time = 0;
-    let mut offset = 0;
+    limit_idle_time_from_start(events, limit, true)
+}
+
+pub fn limit_idle_time_from_start(
+    events: impl Iterator<Item = Result<Event>>,
+    limit: f64,
+    clamp_start: bool,
+) -> impl Iterator<Item = Result<Event>> {
+    let mut limiter = IdleTimeLimiter::new(limit, clamp_start);
 
     events.map(move |event| {
         event.map(|event| {
-            let delay = event.time - prev_time;
-
-            if delay > limit {
-                offset += delay - limit;
-            }
-
-            prev_time = event.time;
-            let time = event.time - offset;
+            let time = limiter.limit(event.time);
 
             Event { time, ..event }
         })
     })
 }
 
+struct IdleTimeLimiter {
+    limit: u64,
+    prev_time: Option<u64>,
+    offset: u64,
+}
+
+impl IdleTimeLimiter {
+    fn new(limit: f64, clamp_start: bool) -> Self {
+        Self {
+            limit: Self::micros(limit),
+            prev_time: if clamp_start { Some(0) } else { None },
+            offset: 0,
+        }
+    }
+
+    // Non-positive limits (and NaN) disable limiting, instead of collapsing
+    // all gaps to zero
+    fn micros(limit: f64) -> u64 {
+        if limit > 0.0 {
+            (limit * 1_000_000.0) as u64
+        } else {
+            u64::MAX
+        }
+    }
+
+    fn limit(&mut self, time: u64) -> u64 {
+        let prev_time = *self.prev_time.get_or_insert(time);
+        let time = time.max(prev_time);
+        let delay = time - prev_time;
+
+        if delay > self.limit {
+            self.offset += delay - self.limit;
+        }
+
+        self.prev_time = Some(time);
+
+        time - self.offset
+    }
+}
+
 pub fn accelerate(
     events: impl Iterator<Item = Result<Event>>,
     speed: f64,
-) -> impl Iterator<Item = Result<Event>> {
-    events.map(move |event| {
+) -> Result<impl Iterator<Item = Result<Event>>> {
+    if !speed.is_finite() || speed <= 0.0 {
+        bail!("invalid speed {speed}, must be a positive number");
+    }
+
+    Ok(events.map(move |event| {
         event.map(|event| {
             let time = ((event.time as f64) / speed) as u64;
 
             Event { time, ..event }
         })
-    })
+    }))
+}
+
+impl Timing {
+    pub fn apply<'a>(
+        &self,
+        events: impl Iterator<Item = Result<Event>> + 'a,
+    ) -> Result<Box<dyn Iterator<Item = Result<Event>> + 'a>> {
+        let events: Box<dyn Iterator<Item = Result<Event>> + 'a> = match self.idle_time_limit {
+            Some(limit) => Box::new(limit_idle_time(events, limit)),
+            None => Box::new(events),
+        };
+
+        match self.speed {
+            Some(speed) => Ok(Box::new(accelerate(events, speed)?)),
+            None => Ok(events),
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{Asciicast, Encoder, Event, EventData, Header};
+    use super::{Asciicast, Encoder, Error, Event, EventData, Header, Timing, Writer};
     use crate::tty;
     use anyhow::Result;
     use rgb::RGB8;
-    use std::collections::HashMap;
+    use std::collections::BTreeMap;
 
     #[test]
     fn open_v1_minimal() {
-        let Asciicast { header, events } =
+        let Asciicast { header, events, .. } =
             super::open_from_path("tests/casts/minimal.json").unwrap();
 
         let events = events.collect::<Result<Vec<Event>>>().unwrap();
@@ -174,7 +412,8 @@ mod tests {
 
     #[test]
     fn open_v1_full() {
-        let Asciicast { header, events } = super::open_from_path("tests/casts/full.json").unwrap();
+        let Asciicast { header, events, .. } =
+            super::open_from_path("tests/casts/full.json").unwrap();
         let events = events.collect::<Result<Vec<Event>>>().unwrap();
 
         assert_eq!((header.cols, header.rows), (100, 50));
@@ -182,16 +421,41 @@ mod tests {
         assert_eq!(events[0].time, 1);
         assert!(matches!(events[0].data, EventData::Output(ref s) if s == "ż"));
 
-        assert_eq!(events[1].time, 1000000);
+        assert_eq!(events[1].time, 1000001);
         assert!(matches!(events[1].data, EventData::Output(ref s) if s == "ółć"));
 
-        assert_eq!(events[2].time, 10500000);
+        assert_eq!(events[2].time, 11500001);
         assert!(matches!(events[2].data, EventData::Output(ref s) if s == "\r\n"));
     }
 
+    #[test]
+    fn open_from_path_missing_file() {
+        let e = super::open_from_path("tests/casts/missing.cast")
+            .err()
+            .unwrap();
+
+        assert!(matches!(e.downcast_ref(), Some(Error::Io(_))));
+        assert!(format!("{e:#}").starts_with("can't open asciicast file: "));
+    }
+
+    #[test]
+    fn open_v1_delta_times() {
+        let Asciicast { events, .. } = super::open_from_path("tests/casts/deltas.json").unwrap();
+
+        assert_eq!(
+            output(events),
+            vec![
+                (500_000, "a".to_owned()),
+                (750_000, "b".to_owned()),
+                (750_000, "c".to_owned()),
+                (1_875_000, "d".to_owned()),
+            ]
+        );
+    }
+
     #[test]
     fn open_v2_minimal() {
-        let Asciicast { header, events } =
+        let Asciicast { header, events, .. } =
             super::open_from_path("tests/casts/minimal.cast").unwrap();
         let events = events.collect::<Result<Vec<Event>>>().unwrap();
 
@@ -204,7 +468,8 @@ mod tests {
 
     #[test]
     fn open_v2_full() {
-        let Asciicast { header, events } = super::open_from_path("tests/casts/full.cast").unwrap();
+        let Asciicast { header, events, .. } =
+            super::open_from_path("tests/casts/full.cast").unwrap();
         let events = events.take(5).collect::<Result<Vec<Event>>>().unwrap();
         let theme = header.theme.unwrap();
 
@@ -231,6 +496,284 @@ mod tests {
         assert!(matches!(events[4].data, EventData::Output(ref s) if s == "\r\n"));
     }
 
+    #[test]
+    fn open_v2_gzipped() {
+        let Asciicast { header, events, .. } =
+            super::open_from_path("tests/casts/demo.cast.gz").unwrap();
+        let events = output(events);
+
+        let Asciicast {
+            header: expected_header,
+            events: expected_events,
+            ..
+        } = super::open_from_path("tests/casts/demo.cast").unwrap();
+        let expected_events = output(expected_events);
+
+        assert_eq!(
+            (header.cols, header.rows),
+            (expected_header.cols, expected_header.rows)
+        );
+
+        assert!(!events.is_empty());
+        assert_eq!(events, expected_events);
+    }
+
+    #[test]
+    fn open_v2_crlf() {
+        let Asciicast { header, events, .. } =
+            super::open_from_path("tests/casts/crlf.cast").unwrap();
+        let events = events.collect::<Result<Vec<Event>>>().unwrap();
+
+        assert_eq!((header.cols, header.rows), (100, 50));
+        assert_eq!(events.len(), 2);
+
+        assert_eq!(events[0].time, 1_230_000);
+        assert!(matches!(events[0].data, EventData::Output(ref s) if s == "hello"));
+
+        assert_eq!(events[1].time, 2_000_000);
+        assert!(matches!(events[1].data, EventData::Output(ref s) if s == "world\r\n"));
+    }
+
+    #[test]
+    fn open_v2_bom() {
+        let Asciicast {
+            version,
+            header,
+            events,
+        } = super::open_from_path("tests/casts/bom.cast").unwrap();
+
+        assert_eq!(version, 2);
+        assert_eq!((header.cols, header.rows), (80, 24));
+        assert_eq!(output(events), vec![(500_000, "foo".to_owned())]);
+    }
+
+    #[test]
+    fn open_v2_float_timestamp() {
+        let Asciicast {
+            version,
+            header,
+            events,
+            ..
+        } = super::open_from_path("tests/casts/float-timestamp.cast").unwrap();
+        let events = events.collect::<Result<Vec<Event>>>().unwrap();
+
+        assert_eq!(version, 2);
+        assert_eq!(header.timestamp, Some(1704719152));
+        assert_eq!(events.len(), 1);
+    }
+
+    #[test]
+    fn open_v2_extra_event_elements() {
+        let Asciicast { events, .. } =
+            super::open_from_path("tests/casts/extra-elements.cast").unwrap();
+        let events = events.collect::<Result<Vec<Event>>>().unwrap();
+
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[1].time, 1_000_000);
+        assert!(matches!(events[1].data, EventData::Output(ref s) if s == "hi"));
+
+        assert!(super::v2::parse_event(r#"[1.0, "o"]"#.to_owned()).is_err());
+    }
+
+    #[test]
+    fn open_v2_invalid_event() {
+        let Asciicast { events, .. } =
+            super::open_from_path("tests/casts/invalid-event.cast").unwrap();
+        let events = events.collect::<Vec<_>>();
+
+        assert!(events[0].is_ok());
+
+        let error = events[1].as_ref().err().unwrap().to_string();
+
+        assert!(error.starts_with("line 3: "), "{error}");
+    }
+
+    #[test]
+    fn open_v2_missing_size() {
+        let result = super::open_from_path("tests/casts/missing-height.cast");
+        let e = result.err().unwrap();
+
+        assert!(matches!(e.downcast_ref(), Some(Error::MissingSize)));
+        assert_eq!(e.root_cause().to_string(), "header missing width/height");
+    }
+
+    #[test]
+    fn open_errors() {
+        let error = |data: &[u8]| super::open(data).err().unwrap();
+
+        assert!(matches!(error(b"").downcast_ref(), Some(Error::Empty)));
+
+        assert!(matches!(
+            error(b"{\"version\": 4, \"width\": 80, \"height\": 24}\n").downcast_ref(),
+            Some(Error::UnsupportedVersion(4))
+        ));
+
+        assert!(matches!(
+            error(b"{\"version\": 5, \"width\": 80, \"height\": 24, \"stdout\": []}")
+                .downcast_ref(),
+            Some(Error::UnsupportedVersion(5))
+        ));
+
+        assert!(matches!(
+            error(b"{\"version\": 1, \"width\": 80,").downcast_ref(),
+            Some(Error::Json(_))
+        ));
+
+        assert!(matches!(
+            error(b"\xff\xfe\n").downcast_ref(),
+            Some(Error::Io(_))
+        ));
+
+        let e = super::open_from_path("tests/casts/non-existent.cast")
+            .err()
+            .unwrap();
+
+        assert!(matches!(e.downcast_ref(), Some(Error::Io(_))));
+    }
+
+    #[test]
+    fn open_piped() {
+        let plain = std::fs::read("tests/casts/demo.cast").unwrap();
+        let gzipped = std::fs::read("tests/casts/demo.cast.gz").unwrap();
+
+        let Asciicast { events, .. } = super::open_maybe_gzipped(&plain[..]).unwrap();
+        let plain_events = output(events);
+
+        let Asciicast { events, .. } = super::open_maybe_gzipped(&gzipped[..]).unwrap();
+        let gzipped_events = output(events);
+
+        assert!(!plain_events.is_empty());
+        assert_eq!(plain_events, gzipped_events);
+    }
+
+    #[test]
+    fn open_v3_minimal() {
+        let Asciicast { header, events, .. } =
+            super::open_from_path("tests/casts/minimal-v3.cast").unwrap();
+        let events = events.collect::<Result<Vec<Event>>>().unwrap();
+
+        assert_eq!((header.cols, header.rows), (100, 50));
+        assert!(header.theme.is_none());
+
+        assert_eq!(events[0].time, 1230000);
+        assert!(matches!(events[0].data, EventData::Output(ref s) if s == "hello"));
+    }
+
+    #[test]
+    fn open_v3_full() {
+        let Asciicast { header, events, .. } =
+            super::open_from_path("tests/casts/full-v3.cast").unwrap();
+        let events = events.collect::<Result<Vec<Event>>>().unwrap();
+        let theme = header.theme.unwrap();
+
+        assert_eq!((header.cols, header.rows), (100, 50));
+        assert_eq!(header.command, Some("/bin/bash".to_owned()));
+        assert_eq!(theme.fg, RGB8::new(0, 0, 0));
+        assert_eq!(theme.palette[0], RGB8::new(0x24, 0x1f, 0x31));
+
+        assert_eq!(events[0].time, 1);
+        assert!(matches!(events[0].data, EventData::Output(ref s) if s == "ż"));
+
+        assert_eq!(events[1].time, 1_000_000);
+        assert!(matches!(events[1].data, EventData::Output(ref s) if s == "ółć"));
+
+        assert_eq!(events[2].time, 2_300_000);
+        assert!(matches!(events[2].data, EventData::Input(ref s) if s == "\n"));
+
+        assert_eq!(events[3].time, 5_600_001);
+        assert!(
+            matches!(events[3].data, EventData::Resize(ref cols, ref rows) if *cols == 80 && *rows == 40)
+        );
+
+        assert_eq!(events[4].time, 10_500_000);
+        assert!(matches!(events[4].data, EventData::Output(ref s) if s == "\r\n"));
+
+        assert_eq!(events[5].time, 10_500_000);
+        assert!(matches!(events[5].data, EventData::Other(ref c, ref s) if c == "x" && s == "0"));
+    }
+
+    #[test]
+    fn other_event_code_round_trip() {
+        let event = super::v2::parse_event(r#"[1.0, "x1", "data"]"#.to_owned()).unwrap();
+
+        assert!(matches!(event.data, EventData::Other(ref c, ref s) if c == "x1" && s == "data"));
+        assert_eq!(Encoder::new(0).event(&event), b"[1.0, \"x1\", \"data\"]\n");
+    }
+
+    #[test]
+    fn marker_label() {
+        let cast = "{\"version\":2,\"width\":80,\"height\":24}\n[12.5, \"m\", \"chapter one\"]\n";
+        let Asciicast { events, .. } = super::open(cast.as_bytes()).unwrap();
+        let events = events.collect::<Result<Vec<Event>>>().unwrap();
+
+        assert_eq!(events[0].time, 12_500_000);
+        assert!(matches!(events[0].data, EventData::Marker(ref s) if s == "chapter one"));
+
+        let lines = parse(Encoder::new(0).event(&events[0]));
+
+        assert_eq!(lines[0][1], "m");
+        assert_eq!(lines[0][2], "chapter one");
+    }
+
+    #[test]
+    fn concat() {
+        let mut data = Vec::new();
+
+        {
+            let mut writer = Writer::new(&mut data, 0);
+            super::concat(
+                ["tests/casts/minimal.json", "tests/casts/demo.cast"].map(super::open_from_path),
+                &mut writer,
+            )
+            .unwrap();
+        }
+
+        let Asciicast { header, events, .. } = super::open(&data[..]).unwrap();
+        let events = events.collect::<Result<Vec<Event>>>().unwrap();
+        let offset = super::get_duration("tests/casts/minimal.json").unwrap();
+        let duration = super::get_duration("tests/casts/demo.cast").unwrap();
+
+        assert_eq!((header.cols, header.rows), (100, 50));
+
+        assert_eq!(events[0].time, 1_230_000);
+        assert!(matches!(events[0].data, EventData::Output(ref s) if s == "hello"));
+
+        assert_eq!(events[1].time, offset);
+        assert!(matches!(events[1].data, EventData::Resize(75, 18)));
+
+        assert_eq!(events[2].time, offset + 89_436);
+        assert_eq!(events.last().unwrap().time, offset + duration);
+    }
+
+    #[test]
+    fn duration_of() {
+        let events = [(0u64, "foo"), (1_500_000, "bar"), (4_000_001, "baz")]
+            .map(|(time, output)| Ok(Event::output(time, output.to_owned())));
+
+        assert_eq!(super::duration_of(events.into_iter()).unwrap(), 4_000_001);
+        assert_eq!(super::duration_of(std::iter::empty()).unwrap(), 0);
+    }
+
+    #[test]
+    fn open_v2_header_metadata() {
+        let Asciicast { header, .. } = super::open_from_path("tests/casts/demo.cast").unwrap();
+
+        assert_eq!((header.cols, header.rows), (75, 18));
+        assert_eq!(header.timestamp, Some(1509091818));
+        assert_eq!(header.idle_time_limit, Some(2.0));
+        assert_eq!(header.env.unwrap()["SHELL"], "/usr/local/bin/fish");
+    }
+
+    #[test]
+    fn open_v1_header_metadata() {
+        let Asciicast { header, .. } = super::open_from_path("tests/casts/full.json").unwrap();
+
+        assert_eq!(header.command, Some("/bin/bash".to_owned()));
+        assert!(header.title.is_none());
+        assert!(header.timestamp.is_none());
+        assert!(header.idle_time_limit.is_none());
+    }
+
     #[test]
     fn encoder() {
         let mut data = Vec::new();
@@ -244,6 +787,7 @@ mod tests {
             title: None,
             env: Default::default(),
             theme: None,
+            extra: Default::default(),
         };
 
         let mut enc = Encoder::new(0);
@@ -279,12 +823,215 @@ mod tests {
         assert_eq!(lines[5][2], "żółć");
     }
 
+    #[test]
+    fn open_v2_pretty_header() {
+        let cast = "{\n  \"version\": 2,\n  \"width\": 80,\n  \"height\": 24,\n  \"title\": \"Demo\",\n  \"env\": {\n    \"SHELL\": \"/bin/sh\"\n  }\n}\n[1.0, \"o\", \"foo\"]\n";
+        let Asciicast { header, events, .. } = super::open(cast.as_bytes()).unwrap();
+
+        assert_eq!((header.cols, header.rows), (80, 24));
+        assert_eq!(header.title.as_deref(), Some("Demo"));
+        assert_eq!(header.env.unwrap()["SHELL"], "/bin/sh");
+        assert_eq!(output(events), vec![(1_000_000, "foo".to_owned())]);
+    }
+
+    #[test]
+    fn writer_flush_on_marker() {
+        struct FlushCounter(usize);
+
+        impl std::io::Write for FlushCounter {
+            fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+                Ok(buf.len())
+            }
+
+            fn flush(&mut self) -> std::io::Result<()> {
+                self.0 += 1;
+
+                Ok(())
+            }
+        }
+
+        let mut counter = FlushCounter(0);
+
+        Writer::new(&mut counter, 0)
+            .write_event(&Event::output(1_000_000, "foo\n".to_owned()))
+            .unwrap();
+
+        assert_eq!(counter.0, 0);
+
+        Writer::new(&mut counter, 0)
+            .write_event(&Event::marker(1_000_000, "intro".to_owned()))
+            .unwrap();
+
+        assert_eq!(counter.0, 1);
+    }
+
+    #[test]
+    fn writer_buffered() {
+        let mut data = Vec::new();
+        let mut writer = Writer::buffered(&mut data, 0, 64 * 1024);
+        writer.write_header(&Header::default()).unwrap();
+
+        for i in 0..10_000 {
+            writer
+                .write_event(&Event::output(i * 1000, format!("line {i}\r\n")))
+                .unwrap();
+        }
+
+        writer.flush().unwrap();
+        drop(writer);
+
+        let Asciicast { events, .. } = super::open(&data[..]).unwrap();
+        let output = output(events);
+
+        assert_eq!(output.len(), 10_000);
+        assert_eq!(output[0], (0, "line 0\r\n".to_owned()));
+        assert_eq!(output[9_999], (9_999_000, "line 9999\r\n".to_owned()));
+    }
+
+    #[test]
+    fn writer_negative_time_offset() {
+        let mut data = Vec::new();
+
+        {
+            let mut writer = Writer::new(&mut data, -2_000_000);
+
+            for (time, output) in [(1_000_000, "foo"), (2_500_000, "bar"), (4_000_000, "baz")] {
+                writer
+                    .write_event(&Event::output(time, output.to_owned()))
+                    .unwrap();
+            }
+        }
+
+        let lines = parse(data);
+
+        assert_eq!(lines[0][0], 0.0);
+        assert_eq!(lines[0][2], "foo");
+        assert_eq!(lines[1][0], 0.5);
+        assert_eq!(lines[2][0], 2.0);
+    }
+
+    #[test]
+    fn writer_time_format() {
+        let mut data = Vec::new();
+
+        {
+            let mut writer = Writer::new(&mut data, 0);
+            writer
+                .write_event(&Event::output(1_000_001, "a".to_owned()))
+                .unwrap();
+            writer
+                .write_event(&Event::output(2_000_000, "b".to_owned()))
+                .unwrap();
+        }
+
+        assert_eq!(
+            String::from_utf8(data).unwrap(),
+            "[1.000001, \"o\", \"a\"]\n[2.0, \"o\", \"b\"]\n"
+        );
+    }
+
+    #[test]
+    fn filter_env() {
+        let vars = [
+            ("SHELL", "/bin/zsh"),
+            ("TERM", "xterm-256color"),
+            ("AWS_SECRET_ACCESS_KEY", "s3cr3t"),
+            ("HOME", "/home/me"),
+        ]
+        .map(|(k, v)| (k.to_owned(), v.to_owned()));
+
+        let env = super::filter_env(vars.into_iter(), &["SHELL", "TERM", "EDITOR"]);
+
+        assert_eq!(env.len(), 2);
+        assert_eq!(env["SHELL"], "/bin/zsh");
+        assert_eq!(env["TERM"], "xterm-256color");
+    }
+
+    #[test]
+    fn header_default_env() {
+        let vars = [
+            ("SHELL", "/bin/bash"),
+            ("TERM", "screen"),
+            ("ASCIINEMA_TEST_DEFAULT_ENV", "1"),
+            ("PATH", "/usr/bin"),
+        ]
+        .map(|(k, v)| (k.to_owned(), v.to_owned()));
+
+        let env = Header::default_env_from(vars.into_iter());
+
+        assert_eq!(
+            env.into_iter().collect::<Vec<_>>(),
+            vec![
+                ("SHELL".to_owned(), "/bin/bash".to_owned()),
+                ("TERM".to_owned(), "screen".to_owned())
+            ]
+        );
+    }
+
+    #[test]
+    fn v2_header_schema() {
+        let schema = super::v2_header_schema();
+
+        assert_eq!(schema["type"], "object");
+        assert_eq!(
+            schema["required"],
+            serde_json::json!(["version", "width", "height"])
+        );
+
+        let properties = schema["properties"].as_object().unwrap();
+
+        for field in [
+            "version",
+            "width",
+            "height",
+            "timestamp",
+            "idle_time_limit",
+            "command",
+            "title",
+            "env",
+            "theme",
+        ] {
+            assert!(properties.contains_key(field), "{field}");
+        }
+    }
+
+    #[test]
+    fn theme_round_trip() {
+        let Asciicast { header, .. } = super::open_from_path("tests/casts/full.cast").unwrap();
+        let data = Encoder::new(0).header(&header);
+        let Asciicast {
+            header: decoded, ..
+        } = super::open(&data[..]).unwrap();
+        let theme = header.theme.unwrap();
+        let decoded = decoded.theme.unwrap();
+
+        assert_eq!(decoded.fg, theme.fg);
+        assert_eq!(decoded.bg, theme.bg);
+        assert_eq!(decoded.palette, theme.palette);
+    }
+
+    #[test]
+    fn unknown_header_fields_round_trip() {
+        let data = b"{\"version\": 2, \"width\": 80, \"height\": 24, \"foo\": {\"bar\": [1, 2]}}\n";
+        let Asciicast { header, .. } = super::open(&data[..]).unwrap();
+
+        assert_eq!(header.extra["foo"], serde_json::json!({"bar": [1, 2]}));
+
+        let data = Encoder::new(0).header(&header);
+        let Asciicast {
+            header: decoded, ..
+        } = super::open(&data[..]).unwrap();
+
+        assert_eq!(decoded.cols, 80);
+        assert_eq!(decoded.extra, header.extra);
+    }
+
     #[test]
     fn header_encoding() {
         let mut enc = Encoder::new(0);
-        let mut env = HashMap::new();
-        env.insert("SHELL".to_owned(), "/usr/bin/fish".to_owned());
+        let mut env = BTreeMap::new();
         env.insert("TERM".to_owned(), "xterm256-color".to_owned());
+        env.insert("SHELL".to_owned(), "/usr/bin/fish".to_owned());
 
         let theme = tty::Theme {
             fg: RGB8::new(0, 1, 2),
@@ -318,9 +1065,11 @@ mod tests {
             title: Some("Demo".to_owned()),
             env: Some(env),
             theme: Some(theme),
+            extra: Default::default(),
         };
 
         let data = enc.header(&header);
+        let json = String::from_utf8(data.clone()).unwrap();
         let lines = parse(data);
 
         assert_eq!(lines[0]["version"], 2);
@@ -333,6 +1082,7 @@ mod tests {
         assert_eq!(lines[0]["env"].as_object().unwrap().len(), 2);
         assert_eq!(lines[0]["env"]["SHELL"], "/usr/bin/fish");
         assert_eq!(lines[0]["env"]["TERM"], "xterm256-color");
+        assert!(json.contains(r#""env":{"SHELL":"/usr/bin/fish","TERM":"xterm256-color"}"#));
         assert_eq!(lines[0]["theme"]["fg"], "#000102");
         assert_eq!(lines[0]["theme"]["bg"], "#0064c8");
         assert_eq!(lines[0]["theme"]["palette"], "#000000:#0a0b0c:#141516:#1e1f20:#28292a:#323334:#3c3d3e:#464748:#505152:#5a5b5c:#646566:#6e6f70:#78797a:#828384:#8c8d8e:#969798");
@@ -348,18 +1098,112 @@ mod tests {
             .unwrap()
     }
 
+    #[test]
+    fn clone_events() {
+        let Asciicast { events, .. } = super::open_from_path("tests/casts/full.cast").unwrap();
+        let events = events.collect::<Result<Vec<Event>>>().unwrap();
+        let copy = events.clone();
+
+        assert_eq!(copy.len(), events.len());
+
+        for (a, b) in events.iter().zip(copy.iter()) {
+            assert_eq!(a.time, b.time);
+        }
+
+        assert!(matches!(copy[3].data, EventData::Resize(80, 40)));
+    }
+
     #[test]
     fn accelerate() {
         let events = [(0u64, "foo"), (20, "bar"), (50, "baz")]
             .map(|(time, output)| Ok(Event::output(time, output.to_owned())));
 
-        let output = output(super::accelerate(events.into_iter(), 2.0));
+        let output = output(super::accelerate(events.into_iter(), 2.0).unwrap());
 
         assert_eq!(output[0], (0, "foo".to_owned()));
         assert_eq!(output[1], (10, "bar".to_owned()));
         assert_eq!(output[2], (25, "baz".to_owned()));
     }
 
+    #[test]
+    fn timing_apply() {
+        let events = || {
+            [(0u64, "foo"), (1_000_000, "bar"), (6_000_000, "baz")]
+                .map(|(time, output)| Ok(Event::output(time, output.to_owned())))
+                .into_iter()
+        };
+
+        let timing = Timing {
+            idle_time_limit: Some(2.0),
+            speed: Some(2.0),
+        };
+
+        let times = timing
+            .apply(events())
+            .unwrap()
+            .map(|e| e.unwrap().time)
+            .collect::<Vec<_>>();
+
+        assert_eq!(times, vec![0, 500_000, 1_500_000]);
+
+        let times = Timing::default()
+            .apply(events())
+            .unwrap()
+            .map(|e| e.unwrap().time)
+            .collect::<Vec<_>>();
+
+        assert_eq!(times, vec![0, 1_000_000, 6_000_000]);
+    }
+
+    #[test]
+    fn adapters_size_hint() {
+        let events = || {
+            [(0u64, "foo"), (20, "bar"), (50, "baz")]
+                .map(|(time, output)| Ok(Event::output(time, output.to_owned())))
+                .into_iter()
+        };
+
+        let source = events().size_hint();
+
+        assert_eq!(source, (3, Some(3)));
+        assert_eq!(
+            super::accelerate(events(), 2.0).unwrap().size_hint(),
+            source
+        );
+        assert_eq!(super::limit_idle_time(events(), 1.0).size_hint(), source);
+        assert_eq!(super::rebase(events()).size_hint(), source);
+    }
+
+    #[test]
+    fn accelerate_invalid_speed() {
+        for speed in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+            let events = [Ok(Event::output(10, "foo".to_owned()))];
+
+            assert!(super::accelerate(events.into_iter(), speed).is_err());
+        }
+    }
+
+    #[test]
+    fn limit_idle_time_disabled() {
+        let events = || {
+            [
+                (1_000_000u64, "foo"),
+                (6_000_000, "bar"),
+                (20_000_000, "baz"),
+            ]
+            .map(|(time, output)| Ok(Event::output(time, output.to_owned())))
+            .into_iter()
+        };
+
+        for limit in [0.0, -1.0, f64::NAN] {
+            let times = super::limit_idle_time(events(), limit)
+                .map(|e| e.unwrap().time)
+                .collect::<Vec<_>>();
+
+            assert_eq!(times, vec![1_000_000, 6_000_000, 20_000_000]);
+        }
+    }
+
     #[test]
     fn limit_idle_time() {
         let events = [
@@ -380,6 +1224,43 @@ mod tests {
         assert_eq!(events[4], (5_500_000, "quux".to_owned()));
     }
 
+    #[test]
+    fn limit_idle_time_from_start() {
+        let events = || {
+            [
+                (10_000_000, "foo"),
+                (11_000_000, "bar"),
+                (15_000_000, "baz"),
+            ]
+            .map(|(time, output)| Ok(Event::output(time, output.to_owned())))
+            .into_iter()
+        };
+
+        let clamped = output(super::limit_idle_time_from_start(events(), 2.0, true));
+
+        assert_eq!(clamped[0], (2_000_000, "foo".to_owned()));
+        assert_eq!(clamped[1], (3_000_000, "bar".to_owned()));
+        assert_eq!(clamped[2], (5_000_000, "baz".to_owned()));
+
+        let unclamped = output(super::limit_idle_time_from_start(events(), 2.0, false));
+
+        assert_eq!(unclamped[0], (10_000_000, "foo".to_owned()));
+        assert_eq!(unclamped[1], (11_000_000, "bar".to_owned()));
+        assert_eq!(unclamped[2], (13_000_000, "baz".to_owned()));
+    }
+
+    #[test]
+    fn limit_idle_time_out_of_order() {
+        let events = [(5_000_000, "a"), (2_000_000, "b"), (6_000_000, "c")]
+            .map(|(time, output)| Ok(Event::output(time, output.to_owned())));
+
+        let events = output(super::limit_idle_time(events.into_iter(), 2.0));
+
+        assert_eq!(events[0], (2_000_000, "a".to_owned()));
+        assert_eq!(events[1], (2_000_000, "b".to_owned()));
+        assert_eq!(events[2], (3_000_000, "c".to_owned()));
+    }
+
     fn output(events: impl Iterator<Item = Result<Event>>) -> Vec<(u64, String)> {
         events
             .filter_map(|r| {
@@ -8,11 +8,18 @@ where
     use serde::de::Error;
 
     let value: serde_json::Value = Deserialize::deserialize(deserializer)?;
+    let overflow = || D::Error::custom(format!("time out of range: {value}"));
+
+    // integers are converted exactly, without the round trip through f64
+    if let Some(secs) = value.as_u64() {
+        return secs.checked_mul(1_000_000).ok_or_else(overflow);
+    }
 
     let number = value
         .as_f64()
+        .filter(|v| v.is_sign_positive())
         .map(|v| v.to_string())
-        .ok_or(Error::custom("expected number"))?;
+        .ok_or_else(|| Error::custom(format!("expected non-negative number, got {value}")))?;
 
     let parts: Vec<&str> = number.split('.').collect();
 
@@ -25,15 +32,89 @@ where
                 .parse()
                 .map_err(Error::custom)?;
 
-            Ok(secs * 1_000_000 + micros)
+            // sub-microsecond digits are rounded half up
+            let round_up = right.as_bytes().get(6).is_some_and(|d| *d >= b'5');
+
+            secs.checked_mul(1_000_000)
+                .and_then(|t| t.checked_add(micros + round_up as u64))
+                .ok_or_else(overflow)
         }
 
         [number] => {
             let secs: u64 = number.parse().map_err(Error::custom)?;
 
-            Ok(secs * 1_000_000)
+            secs.checked_mul(1_000_000).ok_or_else(overflow)
         }
 
         _ => Err(Error::custom(format!("invalid time format: {value}"))),
     }
 }
+
+// Some recorders write the header timestamp as a float, the fractional
+// part is dropped.
+pub fn deserialize_timestamp<'de, D>(deserializer: D) -> Result<Option<u64>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    use serde::de::Error;
+
+    let value: Option<serde_json::Value> = Deserialize::deserialize(deserializer)?;
+
+    match value {
+        None => Ok(None),
+
+        Some(value) => value
+            .as_u64()
+            .or_else(|| {
+                value
+                    .as_f64()
+                    .filter(|v| v.is_finite() && *v >= 0.0 && *v < u64::MAX as f64)
+                    .map(|v| v as u64)
+            })
+            .map(Some)
+            .ok_or_else(|| D::Error::custom(format!("invalid timestamp: {value}"))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::deserialize_time;
+
+    fn parse(value: &str) -> u64 {
+        try_parse(value).unwrap()
+    }
+
+    fn try_parse(value: &str) -> Result<u64, serde_json::Error> {
+        deserialize_time(&mut serde_json::Deserializer::from_str(value))
+    }
+
+    #[test]
+    fn deserialize_time_padding() {
+        assert_eq!(parse("1"), 1_000_000);
+        assert_eq!(parse("1.5"), 1_500_000);
+        assert_eq!(parse("1.000001"), 1_000_001);
+    }
+
+    #[test]
+    fn deserialize_time_rounding() {
+        assert_eq!(parse("1.1234564"), 1_123_456);
+        assert_eq!(parse("1.1234565"), 1_123_457);
+        assert_eq!(parse("1.12345678"), 1_123_457);
+        assert_eq!(parse("1.9999995"), 2_000_000);
+        assert_eq!(parse("1.9999994"), 1_999_999);
+    }
+
+    #[test]
+    fn deserialize_time_non_numeric() {
+        assert!(try_parse(r#""1.5""#).is_err());
+        assert!(try_parse("null").is_err());
+        assert!(try_parse("-1.5").is_err());
+    }
+
+    #[test]
+    fn deserialize_time_large() {
+        assert_eq!(parse("18446744073709"), 18_446_744_073_709_000_000);
+        assert!(try_parse("18446744073710").is_err());
+        assert!(try_parse("1e300").is_err());
+    }
+}
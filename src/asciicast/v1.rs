@@ -1,8 +1,8 @@
-use super::{Asciicast, Event, Header};
+use super::{Asciicast, Error, Event, Header};
 use crate::asciicast::util::deserialize_time;
 use anyhow::{bail, Result};
 use serde::Deserialize;
-use std::collections::HashMap;
+use std::collections::BTreeMap;
 
 #[derive(Debug, Deserialize)]
 struct V1 {
@@ -11,7 +11,7 @@ struct V1 {
     height: u16,
     command: Option<String>,
     title: Option<String>,
-    env: Option<HashMap<String, String>>,
+    env: Option<BTreeMap<String, String>>,
     stdout: Vec<V1OutputEvent>,
 }
 
@@ -23,10 +23,10 @@ struct V1OutputEvent {
 }
 
 pub fn load(json: String) -> Result<Asciicast<'static>> {
-    let asciicast: V1 = serde_json::from_str(&json)?;
+    let asciicast: V1 = serde_json::from_str(&json).map_err(Error::Json)?;
 
     if asciicast.version != 1 {
-        bail!("unsupported asciicast version")
+        bail!(Error::UnsupportedVersion(asciicast.version))
     }
 
     let header = Header {
@@ -38,14 +38,19 @@ pub fn load(json: String) -> Result<Asciicast<'static>> {
         title: asciicast.title.clone(),
         env: asciicast.env.clone(),
         theme: None,
+        extra: Default::default(),
     };
 
-    let events = Box::new(
-        asciicast
-            .stdout
-            .into_iter()
-            .map(|e| Ok(Event::output(e.time, e.data))),
-    );
+    // v1 frame times are delays since the previous frame
+    let events = Box::new(asciicast.stdout.into_iter().scan(0, |time, e| {
+        *time += e.time;
 
-    Ok(Asciicast { header, events })
+        Some(Ok(Event::output(*time, e.data)))
+    }));
+
+    Ok(Asciicast {
+        version: 1,
+        header,
+        events,
+    })
 }
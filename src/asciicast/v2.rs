@@ -2,25 +2,28 @@ use super::{util, Asciicast, Event, EventData, Header};
 use crate::tty;
 use anyhow::{anyhow, bail, Result};
 use serde::{Deserialize, Deserializer, Serialize};
-use std::collections::HashMap;
+use std::collections::BTreeMap;
 use std::fmt;
-use std::io;
+use std::io::{self, Write};
 
 #[derive(Deserialize)]
 struct V2Header {
     version: u8,
     width: u16,
     height: u16,
+    #[serde(default, deserialize_with = "util::deserialize_timestamp")]
     timestamp: Option<u64>,
     idle_time_limit: Option<f64>,
     command: Option<String>,
     title: Option<String>,
-    env: Option<HashMap<String, String>>,
+    env: Option<BTreeMap<String, String>>,
     theme: Option<V2Theme>,
+    #[serde(flatten)]
+    extra: serde_json::Map<String, serde_json::Value>,
 }
 
 #[derive(Deserialize, Serialize, Clone)]
-struct V2Theme {
+pub(super) struct V2Theme {
     #[serde(deserialize_with = "deserialize_color")]
     fg: RGB8,
     #[serde(deserialize_with = "deserialize_color")]
@@ -35,11 +38,9 @@ struct RGB8(rgb::RGB8);
 #[derive(Clone)]
 struct V2Palette(Vec<RGB8>);
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug)]
 struct V2Event {
-    #[serde(deserialize_with = "util::deserialize_time")]
     time: u64,
-    #[serde(deserialize_with = "deserialize_code")]
     code: V2EventCode,
     data: String,
 }
@@ -50,7 +51,7 @@ enum V2EventCode {
     Input,
     Resize,
     Marker,
-    Other(char),
+    Other(String),
 }
 
 pub struct Parser(V2Header);
@@ -79,21 +80,30 @@ impl Parser {
             title: self.0.title.clone(),
             env: self.0.env.clone(),
             theme: self.0.theme.as_ref().map(|t| t.into()),
+            extra: self.0.extra.clone(),
         };
 
-        let events = Box::new(lines.filter_map(parse_line));
+        let events = Box::new(
+            lines
+                .enumerate()
+                .filter_map(|(i, line)| parse_line(i + 2, line)),
+        );
 
-        Asciicast { header, events }
+        Asciicast {
+            version: 2,
+            header,
+            events,
+        }
     }
 }
 
-fn parse_line(line: io::Result<String>) -> Option<Result<Event>> {
+fn parse_line(n: usize, line: io::Result<String>) -> Option<Result<Event>> {
     match line {
         Ok(line) => {
-            if line.is_empty() {
+            if line.trim().is_empty() {
                 None
             } else {
-                Some(parse_event(line))
+                Some(parse_event(line).map_err(|e| anyhow!("line {n}: {e}")))
             }
         }
 
@@ -101,7 +111,7 @@ fn parse_line(line: io::Result<String>) -> Option<Result<Event>> {
     }
 }
 
-fn parse_event(line: String) -> Result<Event> {
+pub(super) fn parse_event(line: String) -> Result<Event> {
     let event = serde_json::from_str::<V2Event>(&line)?;
 
     let data = match event.code {
@@ -136,6 +146,64 @@ fn parse_event(line: String) -> Result<Event> {
     })
 }
 
+// Events are [time, code, data] arrays. Some producers append extra elements
+// (e.g. metadata), which are ignored.
+impl<'de> Deserialize<'de> for V2Event {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct Time(u64);
+        struct Code(V2EventCode);
+        struct EventVisitor;
+
+        impl<'de> Deserialize<'de> for Time {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                util::deserialize_time(deserializer).map(Time)
+            }
+        }
+
+        impl<'de> Deserialize<'de> for Code {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                deserialize_code(deserializer).map(Code)
+            }
+        }
+
+        impl<'de> serde::de::Visitor<'de> for EventVisitor {
+            type Value = V2Event;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("an array of [time, code, data]")
+            }
+
+            fn visit_seq<A>(self, mut seq: A) -> Result<V2Event, A::Error>
+            where
+                A: serde::de::SeqAccess<'de>,
+            {
+                use serde::de::{Error, IgnoredAny};
+
+                let Time(time) = seq
+                    .next_element()?
+                    .ok_or_else(|| Error::invalid_length(0, &self))?;
+
+                let Code(code) = seq
+                    .next_element()?
+                    .ok_or_else(|| Error::invalid_length(1, &self))?;
+
+                let data = seq
+                    .next_element()?
+                    .ok_or_else(|| Error::invalid_length(2, &self))?;
+
+                while seq.next_element::<IgnoredAny>()?.is_some() {}
+
+                Ok(V2Event { time, code, data })
+            }
+        }
+
+        deserializer.deserialize_seq(EventVisitor)
+    }
+}
+
 fn deserialize_code<'de, D>(deserializer: D) -> Result<V2EventCode, D::Error>
 where
     D: Deserializer<'de>,
@@ -151,21 +219,22 @@ where
         "r" => Ok(Resize),
         "m" => Ok(Marker),
         "" => Err(Error::custom("missing event code")),
-        s => Ok(Other(s.chars().next().unwrap())),
+        s => Ok(Other(s.to_owned())),
     }
 }
 
 pub struct Encoder {
-    time_offset: u64,
+    time_offset: i64,
 }
 
 impl Encoder {
-    pub fn new(time_offset: u64) -> Self {
+    pub fn new(time_offset: i64) -> Self {
         Self { time_offset }
     }
 
     pub fn header(&mut self, header: &Header) -> Vec<u8> {
         let header: V2Header = header.into();
+
         let mut data = serde_json::to_string(&header).unwrap().into_bytes();
         data.push(b'\n');
 
@@ -183,24 +252,138 @@ impl Encoder {
         use EventData::*;
 
         let (code, data) = match &event.data {
-            Output(data) => ('o', serde_json::to_string(data)?),
-            Input(data) => ('i', serde_json::to_string(data)?),
-            Resize(cols, rows) => ('r', serde_json::to_string(&format!("{cols}x{rows}"))?),
-            Marker(data) => ('m', serde_json::to_string(data)?),
-            Other(code, data) => (*code, serde_json::to_string(data)?),
+            Output(data) => ("o", serde_json::to_string(data)?),
+            Input(data) => ("i", serde_json::to_string(data)?),
+            Resize(cols, rows) => ("r", serde_json::to_string(&format!("{cols}x{rows}"))?),
+            Marker(data) => ("m", serde_json::to_string(data)?),
+            Other(code, data) => (code.as_str(), serde_json::to_string(data)?),
         };
 
         Ok(format!(
             "[{}, {}, {}]",
-            format_time(event.time + self.time_offset).trim_end_matches('0'),
+            format_time(event.time.saturating_add_signed(self.time_offset)),
             serde_json::to_string(&code)?,
             data,
         ))
     }
 }
 
+pub struct Writer<W: Write> {
+    writer: Buffer<W>,
+    encoder: Encoder,
+}
+
+enum Buffer<W: Write> {
+    Line(io::LineWriter<W>),
+    Block(io::BufWriter<W>),
+}
+
+impl<W: Write> Writer<W> {
+    pub fn new(writer: W, time_offset: i64) -> Self {
+        Self::with_buffer(Buffer::Line(io::LineWriter::new(writer)), time_offset)
+    }
+
+    // Unlike the line buffered writer returned by new, this one hands data to
+    // the underlying writer only when capacity bytes have accumulated, or on
+    // flush. Faster when writing whole recordings in one go, but flush must be
+    // called to make sure everything's written.
+    pub fn buffered(writer: W, time_offset: i64, capacity: usize) -> Self {
+        Self::with_buffer(
+            Buffer::Block(io::BufWriter::with_capacity(capacity, writer)),
+            time_offset,
+        )
+    }
+
+    fn with_buffer(writer: Buffer<W>, time_offset: i64) -> Self {
+        Self {
+            writer,
+            encoder: Encoder::new(time_offset),
+        }
+    }
+
+    pub fn write_header(&mut self, header: &Header) -> io::Result<()> {
+        self.writer.write_all(&self.encoder.header(header))
+    }
+
+    pub fn write_event(&mut self, event: &Event) -> io::Result<()> {
+        self.writer.write_all(&self.encoder.event(event))?;
+
+        // markers are chapter boundaries, make sure live viewers get them
+        // right away
+        if let EventData::Marker(_) = event.data {
+            self.writer.flush()?;
+        }
+
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+impl<W: Write> Write for Buffer<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Buffer::Line(writer) => writer.write(buf),
+            Buffer::Block(writer) => writer.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Buffer::Line(writer) => writer.flush(),
+            Buffer::Block(writer) => writer.flush(),
+        }
+    }
+}
+
 fn format_time(time: u64) -> String {
-    format!("{}.{:0>6}", time / 1_000_000, time % 1_000_000)
+    let secs = time / 1_000_000;
+    let micros = format!("{:0>6}", time % 1_000_000);
+    let fraction = micros.trim_end_matches('0');
+
+    if fraction.is_empty() {
+        format!("{secs}.0")
+    } else {
+        format!("{secs}.{fraction}")
+    }
+}
+
+// Describes the header written by Encoder::header, keep in sync with the
+// Serialize impl below.
+pub fn header_schema() -> serde_json::Value {
+    let color = serde_json::json!({"type": "string", "pattern": "^#[0-9a-fA-F]{6}$"});
+
+    serde_json::json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "title": "asciicast v2 header",
+        "type": "object",
+        "required": ["version", "width", "height"],
+        "properties": {
+            "version": {"const": 2},
+            "width": {"type": "integer", "minimum": 1, "maximum": u16::MAX},
+            "height": {"type": "integer", "minimum": 1, "maximum": u16::MAX},
+            "timestamp": {"type": "integer", "minimum": 0},
+            "idle_time_limit": {"type": "number", "exclusiveMinimum": 0},
+            "command": {"type": "string"},
+            "title": {"type": "string"},
+            "env": {"type": "object", "additionalProperties": {"type": "string"}},
+            "theme": {
+                "type": "object",
+                "required": ["fg", "bg", "palette"],
+                "properties": {
+                    "fg": color,
+                    "bg": color,
+                    "palette": {
+                        "type": "string",
+                        "pattern": "^#[0-9a-fA-F]{6}(:#[0-9a-fA-F]{6}){7}((:#[0-9a-fA-F]{6}){8})?$"
+                    }
+                }
+            }
+        },
+        "additionalProperties": true
+    })
 }
 
 impl serde::Serialize for V2Header {
@@ -236,6 +419,8 @@ impl serde::Serialize for V2Header {
             len += 1;
         }
 
+        len += self.extra.len();
+
         let mut map = serializer.serialize_map(Some(len))?;
         map.serialize_entry("version", &2)?;
         map.serialize_entry("width", &self.width)?;
@@ -267,6 +452,10 @@ impl serde::Serialize for V2Header {
             map.serialize_entry("theme", &theme)?;
         }
 
+        for (key, value) in &self.extra {
+            map.serialize_entry(key, value)?;
+        }
+
         map.end()
     }
 }
@@ -351,6 +540,7 @@ impl From<&Header> for V2Header {
             title: header.title.clone(),
             env: header.env.clone(),
             theme: header.theme.as_ref().map(|t| t.into()),
+            extra: header.extra.clone(),
         }
     }
 }
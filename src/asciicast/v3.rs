@@ -0,0 +1,94 @@
+use super::v2::{self, V2Theme};
+use super::{Asciicast, Event, Header};
+use anyhow::{anyhow, bail, Result};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::io;
+
+#[derive(Deserialize)]
+struct V3Header {
+    version: u8,
+    term: V3Term,
+    timestamp: Option<u64>,
+    idle_time_limit: Option<f64>,
+    command: Option<String>,
+    title: Option<String>,
+    env: Option<BTreeMap<String, String>>,
+}
+
+#[derive(Deserialize)]
+struct V3Term {
+    cols: u16,
+    rows: u16,
+    theme: Option<V2Theme>,
+}
+
+pub struct Parser(V3Header);
+
+pub fn open(header_line: &str) -> Result<Parser> {
+    let header = serde_json::from_str::<V3Header>(header_line)?;
+
+    if header.version != 3 {
+        bail!("unsupported asciicast version")
+    }
+
+    Ok(Parser(header))
+}
+
+impl Parser {
+    pub fn parse<'a, I: Iterator<Item = io::Result<String>> + 'a>(
+        &self,
+        lines: I,
+    ) -> Asciicast<'a> {
+        let header = Header {
+            cols: self.0.term.cols,
+            rows: self.0.term.rows,
+            timestamp: self.0.timestamp,
+            idle_time_limit: self.0.idle_time_limit,
+            command: self.0.command.clone(),
+            title: self.0.title.clone(),
+            env: self.0.env.clone(),
+            theme: self.0.term.theme.as_ref().map(|t| t.into()),
+            extra: Default::default(),
+        };
+
+        let mut prev_time = 0;
+
+        let events = lines
+            .enumerate()
+            .filter_map(|(i, line)| parse_line(i + 2, line));
+
+        let events = Box::new(events.map(move |event| {
+            event.map(|event| {
+                prev_time += event.time;
+
+                Event {
+                    time: prev_time,
+                    ..event
+                }
+            })
+        }));
+
+        Asciicast {
+            version: 3,
+            header,
+            events,
+        }
+    }
+}
+
+fn parse_line(n: usize, line: io::Result<String>) -> Option<Result<Event>> {
+    match line {
+        Ok(line) => {
+            if line.trim().is_empty() || line.starts_with('#') {
+                None
+            } else {
+                // v3 event lines share v2 syntax, with time being an interval
+                // since the previous event
+                Some(v2::parse_event(line).map_err(|e| anyhow!("line {n}: {e}")))
+            }
+        }
+
+        Err(e) => Some(Err(e.into())),
+    }
+}
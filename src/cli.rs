@@ -1,3 +1,4 @@
+use crate::asciicast;
 use clap::{Args, ValueEnum};
 use clap::{Parser, Subcommand};
 use std::net::SocketAddr;
@@ -39,11 +40,24 @@ pub enum Commands {
     /// Convert a recording into another format
     Convert(Convert),
 
+    /// Print information about a recording
+    Info(Info),
+
+    /// Check that a recording can be read and played back
+    Verify(Verify),
+
     /// Upload a recording to an asciinema server
     Upload(Upload),
 
     /// Authenticate this CLI with an asciinema server account
     Auth(Auth),
+
+    /// Remove the association of this CLI with an asciinema server account
+    Logout(Logout),
+
+    /// Print the JSON schema of the asciicast v2 header
+    #[command(hide = true)]
+    Schema(Schema),
 }
 
 #[derive(Debug, Args)]
@@ -110,13 +124,8 @@ pub struct Play {
     #[arg(value_name = "FILENAME_OR_URL")]
     pub filename: String,
 
-    /// Limit idle time to a given number of seconds
-    #[arg(short, long, value_name = "SECS")]
-    pub idle_time_limit: Option<f64>,
-
-    /// Set playback speed
-    #[arg(short, long)]
-    pub speed: Option<f64>,
+    #[command(flatten)]
+    pub playback: PlaybackOpts,
 
     /// Loop loop loop loop
     #[arg(short, long, name = "loop")]
@@ -127,6 +136,26 @@ pub struct Play {
     pub pause_on_markers: bool,
 }
 
+#[derive(Debug, Args)]
+pub struct PlaybackOpts {
+    /// Limit idle time to a given number of seconds
+    #[arg(short, long, value_name = "SECS")]
+    pub idle_time_limit: Option<f64>,
+
+    /// Set playback speed
+    #[arg(short, long)]
+    pub speed: Option<f64>,
+}
+
+impl PlaybackOpts {
+    pub fn timing(&self) -> asciicast::Timing {
+        asciicast::Timing {
+            idle_time_limit: self.idle_time_limit,
+            speed: self.speed,
+        }
+    }
+}
+
 #[derive(Debug, Args)]
 pub struct Stream {
     /// Enable input capture
@@ -162,6 +191,17 @@ pub struct Stream {
 pub struct Cat {
     #[arg(required = true)]
     pub filename: Vec<String>,
+
+    /// Print raw terminal output instead of asciicast
+    #[arg(long)]
+    pub raw: bool,
+
+    /// Include recorded input in raw output
+    #[arg(long, requires = "raw")]
+    pub include_input: bool,
+
+    #[command(flatten)]
+    pub playback: PlaybackOpts,
 }
 
 #[derive(Debug, Args)]
@@ -178,6 +218,21 @@ pub struct Convert {
     /// Overwrite target file if it already exists
     #[arg(long)]
     pub overwrite: bool,
+
+    #[command(flatten)]
+    pub playback: PlaybackOpts,
+}
+
+#[derive(Debug, Args)]
+pub struct Info {
+    #[arg(value_name = "FILENAME_OR_URL")]
+    pub filename: String,
+}
+
+#[derive(Debug, Args)]
+pub struct Verify {
+    #[arg(value_name = "FILENAME_OR_URL")]
+    pub filename: String,
 }
 
 #[derive(Debug, Args)]
@@ -187,7 +242,17 @@ pub struct Upload {
 }
 
 #[derive(Debug, Args)]
-pub struct Auth {}
+pub struct Auth {
+    /// Print the authentication URL as JSON
+    #[arg(long)]
+    pub json: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct Logout {}
+
+#[derive(Debug, Args)]
+pub struct Schema {}
 
 #[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
 pub enum Format {
@@ -3,6 +3,8 @@ use crate::api;
 use crate::cli;
 use crate::config::Config;
 use anyhow::Result;
+use std::io::{self, Write};
+use url::Url;
 
 impl Command for cli::Auth {
     fn run(self, config: &Config) -> Result<()> {
@@ -10,10 +12,72 @@ impl Command for cli::Auth {
         let server_hostname = server_url.host().unwrap();
         let auth_url = api::get_auth_url(config)?;
 
-        println!("Open the following URL in a web browser to authenticate this asciinema CLI with your {server_hostname} user account:\n");
-        println!("{auth_url}\n");
-        println!("This action will associate all recordings uploaded from this machine (past and future ones) with your account, allowing you to manage them (change the title/theme, delete) at {server_hostname}.");
+        self.print_auth_url(&mut io::stdout(), server_hostname, &auth_url)
+    }
+}
+
+impl cli::Auth {
+    fn print_auth_url(
+        &self,
+        out: &mut dyn Write,
+        server_hostname: impl std::fmt::Display,
+        auth_url: &Url,
+    ) -> Result<()> {
+        if self.json {
+            let info = serde_json::json!({
+                "auth_url": auth_url.as_str(),
+                "hostname": server_hostname.to_string(),
+            });
+
+            writeln!(out, "{info}")?;
+        } else {
+            writeln!(out, "Open the following URL in a web browser to authenticate this asciinema CLI with your {server_hostname} user account:\n")?;
+            writeln!(out, "{auth_url}\n")?;
+            writeln!(out, "This action will associate all recordings uploaded from this machine (past and future ones) with your account, allowing you to manage them (change the title/theme, delete) at {server_hostname}.")?;
+        }
 
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::cli;
+    use url::Url;
+
+    #[test]
+    fn print_auth_url() {
+        let auth = cli::Auth { json: false };
+
+        let url = Url::parse("https://asciinema.example.com/connect/1234").unwrap();
+        let mut out = Vec::new();
+        auth.print_auth_url(&mut out, "asciinema.example.com", &url)
+            .unwrap();
+
+        let out = String::from_utf8(out).unwrap();
+
+        assert!(out.contains("https://asciinema.example.com/connect/1234\n"));
+        assert!(out.contains("with your asciinema.example.com user account"));
+        assert!(out.contains("(change the title/theme, delete) at asciinema.example.com."));
+    }
+
+    #[test]
+    fn print_auth_url_json() {
+        let auth = cli::Auth { json: true };
+
+        let url = Url::parse("https://asciinema.example.com/connect/1234").unwrap();
+        let mut out = Vec::new();
+        auth.print_auth_url(&mut out, "asciinema.example.com", &url)
+            .unwrap();
+
+        let info: serde_json::Value = serde_json::from_slice(&out).unwrap();
+
+        assert_eq!(
+            info["auth_url"],
+            "https://asciinema.example.com/connect/1234"
+        );
+        assert_eq!(info["hostname"], "asciinema.example.com");
+        assert_eq!(info.as_object().unwrap().len(), 2);
+        assert_eq!(out.iter().filter(|b| **b == b'\n').count(), 1);
+    }
+}
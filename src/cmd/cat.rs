@@ -1,37 +1,58 @@
 use super::Command;
-use crate::asciicast;
+use crate::asciicast::{self, EventData};
 use crate::cli;
 use crate::config::Config;
 use anyhow::Result;
-use std::io;
-use std::io::Write;
+use std::io::{self, Write};
+
+const BUFFER_CAPACITY: usize = 64 * 1024;
 
 impl Command for cli::Cat {
     fn run(self, _config: &Config) -> Result<()> {
-        let mut encoder = asciicast::Encoder::new(0);
-        let mut stdout = io::stdout();
-        let mut time_offset: u64 = 0;
-        let mut first = true;
+        if self.raw {
+            self.print_raw()
+        } else {
+            let mut writer = asciicast::Writer::buffered(io::stdout(), 0, BUFFER_CAPACITY);
 
-        for path in self.filename.iter() {
-            let recording = asciicast::open_from_path(path)?;
-            let mut time = time_offset;
+            let recordings = self.filename.iter().map(|path| -> Result<_> {
+                let mut recording = asciicast::open_from_path_or_stdin(path)?;
+                recording.events = self.playback.timing().apply(recording.events)?;
 
-            if first {
-                stdout.write_all(&encoder.header(&recording.header))?;
-                first = false;
-            }
+                Ok(recording)
+            });
+
+            asciicast::concat(recordings, &mut writer)?;
+            writer.flush()?;
+
+            Ok(())
+        }
+    }
+}
+
+impl cli::Cat {
+    fn print_raw(&self) -> Result<()> {
+        let mut stdout = io::stdout().lock();
+
+        for path in self.filename.iter() {
+            let recording = asciicast::open_from_path_or_stdin(path)?;
 
             for event in recording.events {
-                let mut event = event?;
-                time = time_offset + event.time;
-                event.time = time;
-                stdout.write_all(&encoder.event(&event))?;
-            }
+                match event?.data {
+                    EventData::Output(data) => {
+                        stdout.write_all(data.as_bytes())?;
+                    }
 
-            time_offset = time;
+                    EventData::Input(data) if self.include_input => {
+                        stdout.write_all(data.as_bytes())?;
+                    }
+
+                    _ => (),
+                }
+            }
         }
 
+        stdout.flush()?;
+
         Ok(())
     }
 }
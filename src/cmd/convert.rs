@@ -11,7 +11,8 @@ use std::path::Path;
 impl Command for cli::Convert {
     fn run(self, _config: &Config) -> Result<()> {
         let path = util::get_local_path(&self.input_filename)?;
-        let cast = asciicast::open_from_path(&*path)?;
+        let mut cast = asciicast::open_from_path_or_stdin(&*path)?;
+        cast.events = self.playback.timing().apply(cast.events)?;
         let mut encoder = self.get_encoder();
         let mut file = self.open_file()?;
 
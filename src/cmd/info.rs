@@ -0,0 +1,100 @@
+use super::Command;
+use crate::asciicast::{self, Event, EventData};
+use crate::cli;
+use crate::config::Config;
+use crate::util;
+use anyhow::Result;
+use chrono::DateTime;
+
+#[derive(Debug, Default, PartialEq)]
+struct EventStats {
+    duration: u64,
+    output: usize,
+    input: usize,
+    resize: usize,
+    marker: usize,
+    other: usize,
+}
+
+impl Command for cli::Info {
+    fn run(self, _config: &Config) -> Result<()> {
+        let path = util::get_local_path(&self.filename)?;
+        let recording = asciicast::open_from_path_or_stdin(&*path)?;
+        let stats = event_stats(recording.events)?;
+        let header = recording.header;
+
+        println!("version: {}", recording.version);
+        println!("size: {}x{}", header.cols, header.rows);
+        println!("duration: {:.3}s", stats.duration as f64 / 1_000_000.0);
+
+        if let Some(command) = header.command {
+            println!("command: {command}");
+        }
+
+        if let Some(title) = header.title {
+            println!("title: {title}");
+        }
+
+        if let Some(date) = header
+            .timestamp
+            .and_then(|t| DateTime::from_timestamp(t as i64, 0))
+        {
+            println!("date: {}", date.format("%Y-%m-%d %H:%M:%S UTC"));
+        }
+
+        println!("events:");
+        println!("  output: {}", stats.output);
+        println!("  input: {}", stats.input);
+        println!("  resize: {}", stats.resize);
+        println!("  marker: {}", stats.marker);
+
+        if stats.other > 0 {
+            println!("  other: {}", stats.other);
+        }
+
+        Ok(())
+    }
+}
+
+fn event_stats(events: impl Iterator<Item = Result<Event>>) -> Result<EventStats> {
+    let mut stats = EventStats::default();
+
+    for event in events {
+        let event = event?;
+        stats.duration = event.time;
+
+        match event.data {
+            EventData::Output(_) => stats.output += 1,
+            EventData::Input(_) => stats.input += 1,
+            EventData::Resize(_, _) => stats.resize += 1,
+            EventData::Marker(_) => stats.marker += 1,
+            EventData::Other(_, _) => stats.other += 1,
+        }
+    }
+
+    Ok(stats)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::EventStats;
+    use crate::asciicast;
+
+    #[test]
+    fn event_stats() {
+        let recording = asciicast::open_from_path("tests/casts/demo.cast").unwrap();
+        let stats = super::event_stats(recording.events).unwrap();
+
+        assert_eq!(
+            stats,
+            EventStats {
+                duration: 11_893_480,
+                output: 30,
+                input: 9,
+                resize: 0,
+                marker: 0,
+                other: 0,
+            }
+        );
+    }
+}
@@ -0,0 +1,21 @@
+use super::Command;
+use crate::cli;
+use crate::config::Config;
+use anyhow::Result;
+
+impl Command for cli::Logout {
+    fn run(self, config: &Config) -> Result<()> {
+        match config.clear_install_id()? {
+            Some(path) => {
+                println!("Removed install ID from {}.", path.display());
+                println!("Recordings uploaded from this machine from now on won't be associated with your account.");
+            }
+
+            None => {
+                println!("This CLI is not associated with any account, nothing to do.");
+            }
+        }
+
+        Ok(())
+    }
+}
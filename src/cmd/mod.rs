@@ -1,10 +1,15 @@
 pub mod auth;
 pub mod cat;
 pub mod convert;
+pub mod info;
+pub mod logout;
 pub mod play;
 pub mod rec;
+pub mod schema;
 pub mod stream;
 pub mod upload;
+pub mod verify;
+
 use crate::config::Config;
 use crate::notifier;
 use std::collections::HashMap;
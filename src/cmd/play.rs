@@ -6,30 +6,32 @@ use crate::logger;
 use crate::player::{self, KeyBindings};
 use crate::tty;
 use crate::util;
-use anyhow::Result;
+use anyhow::{bail, Result};
 
 impl Command for cli::Play {
     fn run(self, config: &Config) -> Result<()> {
-        let speed = self.speed.or(config.cmd_play_speed()).unwrap_or(1.0);
-        let idle_time_limit = self.idle_time_limit.or(config.cmd_play_idle_time_limit());
+        let timing = asciicast::Timing {
+            idle_time_limit: self
+                .playback
+                .idle_time_limit
+                .or(config.cmd_play_idle_time_limit()),
+            speed: self.playback.speed.or(config.cmd_play_speed()),
+        };
 
         logger::info!("Replaying session from {}", self.filename);
 
+        if self.loop_ && self.filename == "-" {
+            bail!("--loop can't be used when reading from stdin");
+        }
+
         let path = util::get_local_path(&self.filename)?;
 
         let ended = loop {
-            let recording = asciicast::open_from_path(&*path)?;
+            let recording = asciicast::open_from_path_or_stdin(&*path)?;
             let tty = tty::DevTty::open()?;
             let keys = get_key_bindings(config)?;
 
-            let ended = player::play(
-                recording,
-                tty,
-                speed,
-                idle_time_limit,
-                self.pause_on_markers,
-                &keys,
-            )?;
+            let ended = player::play(recording, tty, &timing, self.pause_on_markers, &keys)?;
 
             if !self.loop_ {
                 break ended;
@@ -12,7 +12,7 @@ use crate::recorder::{self, KeyBindings};
 use crate::tty::{self, FixedSizeTty};
 use anyhow::{bail, Result};
 use cli::Format;
-use std::collections::{HashMap, HashSet};
+use std::collections::BTreeMap;
 use std::env;
 use std::fs;
 use std::io::{self, Write};
@@ -208,18 +208,16 @@ impl cli::Record {
         let idle_time_limit = self.idle_time_limit.or(config.cmd_rec_idle_time_limit());
         let command = self.get_command(config);
 
-        let env = self
-            .env
-            .as_ref()
-            .cloned()
-            .or(config.cmd_rec_env())
-            .unwrap_or(String::from("TERM,SHELL"));
+        let env = match self.env.as_ref().cloned().or(config.cmd_rec_env()) {
+            Some(vars) => capture_env(&vars),
+            None => Header::default_env(),
+        };
 
         Metadata {
             idle_time_limit,
             command,
             title: self.title.clone(),
-            env: Some(capture_env(&env)),
+            env: Some(env),
         }
     }
 }
@@ -234,7 +232,7 @@ pub struct Metadata {
     pub idle_time_limit: Option<f64>,
     pub command: Option<String>,
     pub title: Option<String>,
-    pub env: Option<HashMap<String, String>>,
+    pub env: Option<BTreeMap<String, String>>,
 }
 
 impl<W: Write, E: Encoder> Output for FileOutput<W, E> {
@@ -255,6 +253,7 @@ impl<W: Write, E: Encoder> Output for FileOutput<W, E> {
             command: self.metadata.command.as_ref().cloned(),
             title: self.metadata.title.as_ref().cloned(),
             env: self.metadata.env.as_ref().cloned(),
+            extra: Default::default(),
         };
 
         self.writer.write_all(&self.encoder.header(&header))
@@ -287,10 +286,8 @@ fn get_key_bindings(config: &Config) -> Result<KeyBindings> {
     Ok(keys)
 }
 
-fn capture_env(vars: &str) -> HashMap<String, String> {
-    let vars = vars.split(',').collect::<HashSet<_>>();
+fn capture_env(vars: &str) -> BTreeMap<String, String> {
+    let vars = vars.split(',').collect::<Vec<_>>();
 
-    env::vars()
-        .filter(|(k, _v)| vars.contains(&k.as_str()))
-        .collect::<HashMap<_, _>>()
+    asciicast::filter_env(env::vars(), &vars)
 }
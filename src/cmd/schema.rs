@@ -0,0 +1,16 @@
+use super::Command;
+use crate::asciicast;
+use crate::cli;
+use crate::config::Config;
+use anyhow::Result;
+
+impl Command for cli::Schema {
+    fn run(self, _config: &Config) -> Result<()> {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&asciicast::v2_header_schema())?
+        );
+
+        Ok(())
+    }
+}
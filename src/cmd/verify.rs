@@ -0,0 +1,57 @@
+use super::Command;
+use crate::asciicast;
+use crate::cli;
+use crate::config::Config;
+use crate::util;
+use anyhow::{Context, Result};
+use std::path::Path;
+
+impl Command for cli::Verify {
+    fn run(self, _config: &Config) -> Result<()> {
+        let path = util::get_local_path(&self.filename)?;
+        let (count, duration) = verify(&*path).context(format!("{} is invalid", self.filename))?;
+
+        println!(
+            "{}: OK, {count} events, {:.3}s",
+            self.filename,
+            duration as f64 / 1_000_000.0
+        );
+
+        Ok(())
+    }
+}
+
+// Reads all events through the same adapters the player uses, returning the
+// number of events and the playback duration.
+fn verify<S: AsRef<Path>>(path: S) -> Result<(usize, u64)> {
+    let recording = asciicast::open_from_path(path)?;
+    let limit = recording.header.idle_time_limit.unwrap_or(f64::MAX);
+    let events = asciicast::limit_idle_time(recording.events, limit);
+    let mut count = 0;
+    let mut duration = 0;
+
+    for event in asciicast::accelerate(events, 1.0)? {
+        duration = event?.time;
+        count += 1;
+    }
+
+    Ok((count, duration))
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn verify() {
+        let (count, duration) = super::verify("tests/casts/demo.cast").unwrap();
+
+        assert_eq!(count, 39);
+        assert!(duration <= 11_891_762);
+
+        let error = super::verify("tests/casts/invalid-event.cast")
+            .err()
+            .unwrap()
+            .to_string();
+
+        assert!(error.starts_with("line 3: "), "{error}");
+    }
+}
@@ -24,6 +24,7 @@ pub struct Config {
 #[allow(unused)]
 pub struct Server {
     url: Option<String>,
+    proxy: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -76,8 +77,24 @@ pub struct Notifications {
 
 impl Config {
     pub fn new(server_url: Option<String>) -> Result<Self> {
+        if let (Err(_), Ok(url)) = (
+            env::var("ASCIINEMA_SERVER_URL"),
+            env::var("ASCIINEMA_API_URL"),
+        ) {
+            env::set_var("ASCIINEMA_SERVER_URL", url);
+        }
+
+        Self::load(
+            server_url,
+            &home()?,
+            config::Environment::with_prefix("asciinema").separator("_"),
+        )
+    }
+
+    fn load(server_url: Option<String>, home: &Path, env: config::Environment) -> Result<Self> {
         let mut config = config::Config::builder()
             .set_default("server.url", None::<Option<String>>)?
+            .set_default("server.proxy", None::<Option<String>>)?
             .set_default("cmd.rec.input", false)?
             .set_default("cmd.rec.filename", "%Y-%m-%d-%H-%M-%S-{pid}.cast")?
             .set_default("cmd.play.speed", None::<Option<f64>>)?
@@ -85,24 +102,18 @@ impl Config {
             .set_default("notifications.enabled", true)?
             .add_source(config::File::with_name("/etc/asciinema/config.toml").required(false))
             .add_source(
-                config::File::with_name(&user_defaults_path()?.to_string_lossy()).required(false),
+                config::File::with_name(&user_defaults_path(home).to_string_lossy())
+                    .required(false),
             )
             .add_source(
-                config::File::with_name(&user_config_path()?.to_string_lossy()).required(false),
+                config::File::with_name(&user_config_path(home).to_string_lossy()).required(false),
             )
-            .add_source(config::Environment::with_prefix("asciinema").separator("_"));
+            .add_source(env);
 
         if let Some(url) = server_url {
             config = config.set_override("server.url", Some(url))?;
         }
 
-        if let (Err(_), Ok(url)) = (
-            env::var("ASCIINEMA_SERVER_URL"),
-            env::var("ASCIINEMA_API_URL"),
-        ) {
-            env::set_var("ASCIINEMA_SERVER_URL", url);
-        }
-
         Ok(config.build()?.try_deserialize()?)
     }
 
@@ -119,6 +130,10 @@ impl Config {
         }
     }
 
+    pub fn get_server_proxy(&self) -> Option<&str> {
+        self.server.proxy.as_deref()
+    }
+
     pub fn get_install_id(&self) -> Result<String> {
         let path = install_id_path()?;
 
@@ -132,6 +147,16 @@ impl Config {
         }
     }
 
+    pub fn clear_install_id(&self) -> Result<Option<PathBuf>> {
+        let path = install_id_path()?;
+
+        match fs::remove_file(&path) {
+            Ok(()) => Ok(Some(path)),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => bail!(e),
+        }
+    }
+
     pub fn cmd_rec_command(&self) -> Option<String> {
         self.cmd.rec.command.as_ref().cloned()
     }
@@ -234,7 +259,7 @@ fn ask_for_server_url() -> Result<String> {
 }
 
 fn save_default_server_url(url: &str) -> Result<()> {
-    let path = user_defaults_path()?;
+    let path = user_defaults_path(&home()?);
 
     if let Some(dir) = path.parent() {
         fs::create_dir_all(dir)?;
@@ -283,12 +308,12 @@ fn save_install_id(path: &PathBuf, id: &str) -> Result<()> {
     Ok(())
 }
 
-fn user_config_path() -> Result<PathBuf> {
-    Ok(home()?.join("config.toml"))
+fn user_config_path(home: &Path) -> PathBuf {
+    home.join("config.toml")
 }
 
-fn user_defaults_path() -> Result<PathBuf> {
-    Ok(home()?.join("defaults.toml"))
+fn user_defaults_path(home: &Path) -> PathBuf {
+    home.join("defaults.toml")
 }
 
 fn install_id_path() -> Result<PathBuf> {
@@ -341,3 +366,58 @@ fn parse_key<S: AsRef<str>>(key: S) -> Result<Key> {
 
     Err(anyhow!("invalid key definition '{key}'"))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::Config;
+    use std::collections::HashMap;
+    use std::fs;
+
+    fn env(vars: &[(&str, &str)]) -> config::Environment {
+        let vars = vars
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect::<HashMap<_, _>>();
+
+        config::Environment::with_prefix("asciinema")
+            .separator("_")
+            .source(Some(vars))
+    }
+
+    #[test]
+    fn server_url() {
+        let home = tempfile::tempdir().unwrap();
+        fs::write(
+            home.path().join("defaults.toml"),
+            "[server]\nurl = \"https://asciinema.example.com\"\n",
+        )
+        .unwrap();
+
+        let load = |vars: &[(&str, &str)]| Config::load(None, home.path(), env(vars)).unwrap();
+
+        let url = load(&[("ASCIINEMA_SERVER_URL", "https://self-hosted.example.com")])
+            .get_server_url()
+            .unwrap();
+
+        assert_eq!(url.as_str(), "https://self-hosted.example.com/");
+
+        assert!(load(&[("ASCIINEMA_SERVER_URL", "not a url")])
+            .get_server_url()
+            .is_err());
+
+        let url = load(&[]).get_server_url().unwrap();
+
+        assert_eq!(url.as_str(), "https://asciinema.example.com/");
+
+        let url = Config::load(
+            Some("https://cli.example.com".to_owned()),
+            home.path(),
+            env(&[("ASCIINEMA_SERVER_URL", "https://self-hosted.example.com")]),
+        )
+        .unwrap()
+        .get_server_url()
+        .unwrap();
+
+        assert_eq!(url.as_str(), "https://cli.example.com/");
+    }
+}
@@ -7,7 +7,7 @@ pub struct AsciicastEncoder {
 
 impl AsciicastEncoder {
     pub fn new(append: bool, time_offset: u64) -> Self {
-        let inner = Encoder::new(time_offset);
+        let inner = Encoder::new(time_offset as i64);
 
         Self { inner, append }
     }
@@ -30,3 +30,44 @@ impl super::Encoder for AsciicastEncoder {
         Vec::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::AsciicastEncoder;
+    use crate::asciicast::{self, Asciicast, Event, EventData};
+    use crate::encoder::EncoderExt;
+    use anyhow::Result;
+    use std::fs;
+
+    #[test]
+    fn convert_v1_to_v2() {
+        let cast = asciicast::open_from_path("tests/casts/full.json").unwrap();
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+
+        AsciicastEncoder::new(false, 0)
+            .encode_to_file(cast, file.as_file_mut())
+            .unwrap();
+
+        let data = fs::read_to_string(file.path()).unwrap();
+        let header: serde_json::Value = serde_json::from_str(data.lines().next().unwrap()).unwrap();
+
+        assert_eq!(header["version"], 2);
+
+        let Asciicast { header, events, .. } = asciicast::open_from_path(file.path()).unwrap();
+        let events = events.collect::<Result<Vec<Event>>>().unwrap();
+
+        assert_eq!((header.cols, header.rows), (100, 50));
+        assert_eq!(header.command, Some("/bin/bash".to_owned()));
+        assert_eq!(header.env.unwrap()["SHELL"], "/bin/bash");
+        assert_eq!(events.len(), 3);
+
+        assert_eq!(events[0].time, 1);
+        assert!(matches!(events[0].data, EventData::Output(ref s) if s == "ż"));
+
+        assert_eq!(events[1].time, 1_000_001);
+        assert!(matches!(events[1].data, EventData::Output(ref s) if s == "ółć"));
+
+        assert_eq!(events[2].time, 11_500_001);
+        assert!(matches!(events[2].data, EventData::Output(ref s) if s == "\r\n"));
+    }
+}
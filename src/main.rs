@@ -35,7 +35,11 @@ fn main() -> anyhow::Result<()> {
         Commands::Stream(stream) => stream.run(&config),
         Commands::Cat(cat) => cat.run(&config),
         Commands::Convert(convert) => convert.run(&config),
+        Commands::Info(info) => info.run(&config),
+        Commands::Verify(verify) => verify.run(&config),
         Commands::Upload(upload) => upload.run(&config),
         Commands::Auth(auth) => auth.run(&config),
+        Commands::Logout(logout) => logout.run(&config),
+        Commands::Schema(schema) => schema.run(&config),
     }
 }
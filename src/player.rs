@@ -1,4 +1,4 @@
-use crate::asciicast::{self, Event, EventData};
+use crate::asciicast::{self, Event, EventData, Timing};
 use crate::config::Key;
 use crate::tty::Tty;
 use anyhow::Result;
@@ -8,6 +8,11 @@ use std::io::{self, Write};
 use std::os::unix::io::AsRawFd;
 use std::time::{Duration, Instant};
 
+// XTWINOPS sequences saving and restoring the window title on the terminal's
+// title stack
+const TITLE_PUSH: &str = "\x1b[22;0t";
+const TITLE_POP: &str = "\x1b[23;0t";
+
 pub struct KeyBindings {
     pub quit: Key,
     pub pause: Key,
@@ -28,14 +33,38 @@ impl Default for KeyBindings {
 
 pub fn play(
     recording: asciicast::Asciicast,
-    mut tty: impl Tty,
-    speed: f64,
-    idle_time_limit: Option<f64>,
+    tty: impl Tty,
+    timing: &Timing,
     pause_on_markers: bool,
     keys: &KeyBindings,
 ) -> Result<bool> {
-    let mut events = open_recording(recording, speed, idle_time_limit)?;
+    let title = recording.header.title.clone();
+    let events = open_recording(recording, timing)?;
     let mut stdout = io::stdout();
+
+    if let Some(title) = &title {
+        stdout.write_all(TITLE_PUSH.as_bytes())?;
+        stdout.write_all(title_osc(title).as_bytes())?;
+    }
+
+    let result = play_events(events, tty, &mut stdout, pause_on_markers, keys);
+
+    // The title is restored when playback is interrupted or fails too
+    if title.is_some() {
+        stdout.write_all(TITLE_POP.as_bytes())?;
+        stdout.flush()?;
+    }
+
+    result
+}
+
+fn play_events(
+    mut events: impl Iterator<Item = Result<Event>>,
+    mut tty: impl Tty,
+    stdout: &mut io::Stdout,
+    pause_on_markers: bool,
+    keys: &KeyBindings,
+) -> Result<bool> {
     let mut epoch = Instant::now();
     let mut pause_elapsed_time: Option<u64> = None;
     let mut next_event = events.next().transpose()?;
@@ -127,19 +156,24 @@ pub fn play(
     Ok(true)
 }
 
-fn open_recording(
-    recording: asciicast::Asciicast<'_>,
-    speed: f64,
-    idle_time_limit: Option<f64>,
-) -> Result<impl Iterator<Item = Result<Event>> + '_> {
-    let idle_time_limit = idle_time_limit
-        .or(recording.header.idle_time_limit)
-        .unwrap_or(f64::MAX);
+// Control characters are dropped from the title, as BEL or ESC would end the
+// OSC sequence early and let the rest of the title through as terminal input.
+fn title_osc(title: &str) -> String {
+    let title: String = title.chars().filter(|c| !c.is_control()).collect();
+
+    format!("\x1b]0;{title}\x07")
+}
 
-    let events = asciicast::limit_idle_time(recording.events, idle_time_limit);
-    let events = asciicast::accelerate(events, speed);
+fn open_recording<'a>(
+    recording: asciicast::Asciicast<'a>,
+    timing: &Timing,
+) -> Result<impl Iterator<Item = Result<Event>> + 'a> {
+    let timing = Timing {
+        idle_time_limit: timing.idle_time_limit.or(recording.header.idle_time_limit),
+        ..*timing
+    };
 
-    Ok(events)
+    timing.apply(recording.events)
 }
 
 fn read_input<T: Tty>(tty: &mut T, timeout: i64) -> Result<Option<Vec<u8>>> {
@@ -171,3 +205,58 @@ fn read_input<T: Tty>(tty: &mut T, timeout: i64) -> Result<Option<Vec<u8>>> {
         Ok(None)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::asciicast::{self, Event, EventData, Timing};
+    use anyhow::Result;
+
+    #[test]
+    fn title_osc() {
+        assert_eq!(super::title_osc("Demo"), "\x1b]0;Demo\x07");
+        assert_eq!(super::title_osc("De\x07mo\x1b]"), "\x1b]0;Demo]\x07");
+    }
+
+    #[test]
+    fn open_recording() {
+        let recording = asciicast::open_from_path("tests/casts/full.json").unwrap();
+
+        let timing = Timing {
+            idle_time_limit: None,
+            speed: Some(1000.0),
+        };
+
+        let events = super::open_recording(recording, &timing)
+            .unwrap()
+            .collect::<Result<Vec<Event>>>()
+            .unwrap();
+
+        let output = events
+            .iter()
+            .filter_map(|e| match &e.data {
+                EventData::Output(data) => Some(data.as_str()),
+                _ => None,
+            })
+            .collect::<String>();
+
+        assert_eq!(output, "żółć\r\n");
+        assert_eq!(events.last().unwrap().time, 11_500);
+    }
+
+    #[test]
+    fn open_recording_with_idle_time_limit() {
+        let recording = asciicast::open_from_path("tests/casts/full.json").unwrap();
+
+        let timing = Timing {
+            idle_time_limit: Some(1.0),
+            speed: Some(2.0),
+        };
+
+        let times = super::open_recording(recording, &timing)
+            .unwrap()
+            .map(|e| e.unwrap().time)
+            .collect::<Vec<_>>();
+
+        assert_eq!(times, vec![0, 500_000, 1_000_000]);
+    }
+}